@@ -6,6 +6,7 @@
 use crate::capabilities::MemoryAllocationCapability;
 use crate::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
 use crate::kernel::Kernel;
+use crate::platform::mpu;
 use crate::process;
 use crate::process::ProcessId;
 use crate::processbuffer::ReadableProcessBuffer;
@@ -17,33 +18,174 @@ pub const DRIVER_NUM: usize = 0x10000;
 
 /// Ids for read-only allow buffers
 mod ro_allow {
+    /// Buffer holding the package name a discovery (`command` 1) searches for.
     pub(super) const SEARCH: usize = 0;
-    /// The number of allow buffers the kernel stores for this grant
-    pub(super) const COUNT: usize = 1;
+    /// Buffer holding the packed client allowlist a service publishes with
+    /// `command` 6. Each entry is a `[len: u8][name bytes]` record.
+    pub(super) const ALLOWLIST: usize = 1;
+    /// Buffer holding the name a service registers for an endpoint with
+    /// `command` 8.
+    pub(super) const ENDPOINT: usize = 2;
+    /// Base index of the per-peer read-only shared buffers. The read-only
+    /// buffer a process lends to a peer lives at `SHARE_RO + peer_index`, where
+    /// `peer_index` is the process index of the process it is lent to,
+    /// mirroring how the read-write buffers are indexed by process index.
+    pub(super) const SHARE_RO: usize = 3;
 }
 
+/// Maximum number of named endpoints a process may host in addition to the
+/// primary service keyed by its application name. The endpoint table lives
+/// inline in the grant, so it is a fixed size.
+const MAX_ENDPOINTS: usize = 4;
+
+/// Maximum length of an endpoint name stored inline in the grant.
+const ENDPOINT_NAME_LEN: usize = 16;
+
+/// Maximum length of the package name a discovery (`command` 1) searches for.
+/// The search string is copied out of the caller's grant into a fixed stack
+/// buffer before discovery walks the other processes, so a search longer than
+/// this cannot be represented and matches nothing — acceptable, as process and
+/// endpoint names are far shorter.
+const DISCOVERY_SEARCH_MAX: usize = 64;
+
+/// A service descriptor packs the target process index in its low bits and an
+/// endpoint selector in its high bits: selector 0 is the primary service
+/// upcall (the historic single-service behaviour), selector `k` addresses
+/// endpoint `k - 1`. This keeps a bare process index a valid descriptor for
+/// the primary service.
+const ENDPOINT_SELECTOR_SHIFT: usize = 16;
+const PROCESS_INDEX_MASK: usize = (1 << ENDPOINT_SELECTOR_SHIFT) - 1;
+
+/// Build a service descriptor from a process index and endpoint selector.
+const fn make_descriptor(process_index: usize, selector: usize) -> usize {
+    (process_index & PROCESS_INDEX_MASK) | (selector << ENDPOINT_SELECTOR_SHIFT)
+}
+
+/// Maximum number of bytes of packed allowlist a service may publish. The
+/// allowlist lives inline in the service's grant, so it is a fixed size.
+const ALLOWLIST_BUF_LEN: usize = 128;
+
+/// Number of outstanding request/reply correlation tags a process may have in
+/// flight at once. The ring lives inline in the grant, so it is a fixed size.
+const PENDING_RING_LEN: usize = 8;
+
+/// Flag OR'd into the shared-buffer length word of a notify upcall to tell the
+/// receiver that the buffer was lent immutable, i.e. the receiver may only read
+/// it. When the flag is clear the buffer was lent mutable (the historic
+/// read-write behaviour) and may be written back. The flag lives in the length
+/// word rather than the first argument because the first argument carries the
+/// notifying process index, which the receiver passes straight back as the
+/// `target_id` of a command 3/5 reply; corrupting it there would force every
+/// service to mask the bit before replying. Buffer lengths never approach the
+/// top of the address space on the targets IPC runs on, so the high bit is free.
+const READONLY_SHARED_FLAG: usize = 1 << (usize::BITS - 1);
+
 /// Enum to mark which type of upcall is scheduled for the IPC mechanism.
+///
+/// Both variants optionally carry a scalar payload. Carrying it here (rather
+/// than widening `process::Task::IPC`, which lives in the core `process`
+/// module) keeps the task representation and the scheduler's
+/// `schedule_upcall` call site unchanged: a notify that shares a buffer sets
+/// `scalar` to `None`, while a register-only scalar notify carries the inline
+/// payload words.
+///
+/// The scheduler only stores this value in `process::Task::IPC` and hands it
+/// back to `schedule_upcall`; it is matched solely inside this module. Adding
+/// the `scalar` field to the `Service` variant therefore does not touch any
+/// out-of-crate `match` arm.
 #[derive(Copy, Clone, Debug)]
 pub enum IPCUpcallType {
-    /// Indicates that the upcall is for the service upcall handler this
-    /// process has setup.
-    Service,
+    /// Indicates that the upcall is for one of the service upcall handlers this
+    /// process has setup. `slot` is the upcall slot to invoke:
+    /// `SERVICE_UPCALL_NUM` for the primary service, or an endpoint's slot for
+    /// a named endpoint.
+    Service {
+        slot: usize,
+        scalar: Option<(usize, usize)>,
+    },
     /// Indicates that the upcall is from a different service app and will
     /// call one of the client upcalls setup by this process.
-    Client,
+    Client { scalar: Option<(usize, usize)> },
+}
+
+/// A named IPC endpoint hosted by a process in addition to its primary
+/// service. Endpoint `k` is reached through service upcall slot
+/// `CLIENT_UPCALL_NUM_BASE + NUM_PROCS + k` (see `IPC::endpoint_upcall_slot`).
+#[derive(Copy, Clone)]
+struct Endpoint {
+    /// Whether this endpoint slot has been registered with `command` 8.
+    active: bool,
+    /// The endpoint name matched by discovery. Only the first `name_len` bytes
+    /// are valid.
+    name: [u8; ENDPOINT_NAME_LEN],
+    /// Number of valid bytes in `name`.
+    name_len: usize,
+}
+
+impl Default for Endpoint {
+    fn default() -> Self {
+        Self {
+            active: false,
+            name: [0; ENDPOINT_NAME_LEN],
+            name_len: 0,
+        }
+    }
 }
 
 /// State that is stored in each process's grant region to support IPC.
-#[derive(Default)]
-struct IPCData;
-
-/// The upcall setup by a service. Each process can only be one service.
-/// Subscribe with subscribe_num == 0 is how a process registers
-/// itself as an IPC service. Each process can only register as a
-/// single IPC service. The identifier for the IPC service is the
-/// application name stored in the TBF header of the application.
-/// The upcall that is passed to subscribe is called when another
-/// process notifies the server process.
+struct IPCData {
+    /// Whether this process, acting as a service, has published a client
+    /// allowlist. When `false` the service is open to all callers, preserving
+    /// the historic unauthenticated behaviour.
+    allowlist_set: bool,
+    /// Packed allowlist of permitted client package names, stored as a
+    /// sequence of `[len: u8][name bytes]` records. Only the first
+    /// `allowlist_len` bytes are valid.
+    allowlist: [u8; ALLOWLIST_BUF_LEN],
+    /// Number of valid bytes in `allowlist`.
+    allowlist_len: usize,
+    /// Monotonic source of request correlation tags handed out when this
+    /// process issues a scalar request (`command` 4). Tag 0 is reserved to mean
+    /// "no tag", so allocation starts at 1 and skips 0 on wrap.
+    next_tag: u32,
+    /// Ring of outstanding `(tag, peer process index)` pairs for requests this
+    /// process has issued and not yet seen answered.
+    pending: [Option<(u32, usize)>; PENDING_RING_LEN],
+    /// Bitmask, indexed by client process index, of the clients that have
+    /// discovered this process acting as a service. Upcall subscription itself
+    /// is managed by the core kernel and not visible to this driver, so this
+    /// tracks discovery (command 1) — the earliest driver-visible point in the
+    /// subscribe-and-wait sequence — rather than true subscription state. The
+    /// broadcast command (7) fans out to this set, pruning bits for processes
+    /// that no longer exist. Supports up to `u32::BITS` clients, matching the
+    /// small process counts IPC boards are configured with.
+    clients: u32,
+    /// Table of named endpoints this process hosts in addition to its primary
+    /// service, indexed by endpoint number.
+    endpoints: [Endpoint; MAX_ENDPOINTS],
+}
+
+impl Default for IPCData {
+    fn default() -> Self {
+        Self {
+            allowlist_set: false,
+            allowlist: [0; ALLOWLIST_BUF_LEN],
+            allowlist_len: 0,
+            next_tag: 1,
+            pending: [None; PENDING_RING_LEN],
+            clients: 0,
+            endpoints: [Endpoint::default(); MAX_ENDPOINTS],
+        }
+    }
+}
+
+/// The upcall setup by a service for its primary endpoint. Subscribe with
+/// subscribe_num == 0 is how a process registers itself as an IPC service.
+/// The identifier for this primary service is the application name stored in
+/// the TBF header of the application. The upcall that is passed to subscribe
+/// is called when another process notifies the server process. A process may
+/// host additional named endpoints beyond this one; see `command` 8 and
+/// `IPC::endpoint_upcall_slot`.
 const SERVICE_UPCALL_NUM: usize = 0;
 
 /// This const specifies the subscribe_num of the first upcall
@@ -56,16 +198,31 @@ const SERVICE_UPCALL_NUM: usize = 0;
 
 const CLIENT_UPCALL_NUM_BASE: usize = 1;
 
+/// The service upcall slots for named endpoints follow the `NUM_PROCS` client
+/// upcall slots. Endpoint `k` (registered with `command` 8) is invoked through
+/// subscribe_num `CLIENT_UPCALL_NUM_BASE + NUM_PROCS + k`. This base depends on
+/// `NUM_PROCS`, so it is computed where that const generic is in scope rather
+/// than as a module constant.
+
 /// The IPC mechanism struct.
-/// NUM_UPCALLS should always equal NUM_PROCS + 1. The extra upcall
-/// is so processes can register as a service. Once const_evaluatable_checked
-/// is stable we will not need two separate const generic parameters.
+/// NUM_UPCALLS should always equal NUM_PROCS + 1 + MAX_ENDPOINTS: one primary
+/// service upcall, `NUM_PROCS` client upcalls, and one upcall per named
+/// endpoint. The read-only allow count this driver needs is NUM_PROCS + 3 —
+/// three fixed buffers (the discovery search string, the published client
+/// allowlist, and the endpoint registration name) plus the `NUM_PROCS` slots
+/// that hold the immutable buffer each client may lend to a service — which is
+/// always covered by NUM_UPCALLS (NUM_PROCS + 1 + MAX_ENDPOINTS, with
+/// MAX_ENDPOINTS >= 2). Reusing NUM_UPCALLS for `AllowRoCount` keeps the public
+/// type at its original two const generics, so existing board instantiations of
+/// `IPC<NUM_PROCS, NUM_UPCALLS>` continue to compile unchanged; the extra
+/// allow slots are simply never indexed. Once const_evaluatable_checked is
+/// stable the read-only count can be computed directly from NUM_PROCS.
 pub struct IPC<const NUM_PROCS: usize, const NUM_UPCALLS: usize> {
     /// The grant regions for each process that holds the per-process IPC data.
     data: Grant<
         IPCData,
         UpcallCount<NUM_UPCALLS>,
-        AllowRoCount<{ ro_allow::COUNT }>,
+        AllowRoCount<NUM_UPCALLS>,
         AllowRwCount<NUM_PROCS>,
     >,
 }
@@ -91,13 +248,29 @@ impl<const NUM_PROCS: usize, const NUM_UPCALLS: usize> IPC<NUM_PROCS, NUM_UPCALL
     ) -> Result<(), process::Error> {
         self.data
             .enter(schedule_on, |_, schedule_on_kernel_data| {
-                let to_schedule: usize = match cb_type {
-                    IPCUpcallType::Service => SERVICE_UPCALL_NUM,
-                    IPCUpcallType::Client => match called_from.index() {
-                        Some(i) => i + CLIENT_UPCALL_NUM_BASE,
-                        None => panic!("Invalid app issued IPC request"), //TODO: return Error instead
-                    },
+                let (to_schedule, scalar): (usize, Option<(usize, usize)>) = match cb_type {
+                    IPCUpcallType::Service { slot, scalar } => (slot, scalar),
+                    IPCUpcallType::Client { scalar } => {
+                        let slot = match called_from.index() {
+                            Some(i) => i + CLIENT_UPCALL_NUM_BASE,
+                            None => panic!("Invalid app issued IPC request"), //TODO: return Error instead
+                        };
+                        (slot, scalar)
+                    }
                 };
+                // Scalar messages carry their payload inline in the syscall
+                // arguments: deliver it straight to the upcall with no buffer
+                // sharing and no MPU region added.
+                if let Some((payload0, payload1)) = scalar {
+                    let called_from_id = match called_from.index() {
+                        Some(index) => index,
+                        None => return,
+                    };
+                    schedule_on_kernel_data
+                        .schedule_upcall(to_schedule, (called_from_id, payload0, payload1))
+                        .ok();
+                    return;
+                }
                 self.data.enter(called_from, |_, called_from_kernel_data| {
                     // If the other app shared a buffer with us, make
                     // sure we have access to that slice and then call
@@ -109,35 +282,180 @@ impl<const NUM_PROCS: usize, const NUM_UPCALLS: usize> IPC<NUM_PROCS, NUM_UPCALL
                             // If index is invalid, then we cannot notify
                             None => return,
                         };
-                        match called_from_kernel_data.get_readwrite_processbuffer(i) {
-                            Ok(slice) => {
-                                self.data
-                                    .kernel
-                                    .process_map_or(None, schedule_on, |process| {
-                                        process.add_mpu_region(
-                                            slice.ptr(),
-                                            slice.len(),
-                                            slice.len(),
-                                        )
-                                    });
-                                schedule_on_kernel_data
-                                    .schedule_upcall(
-                                        to_schedule,
-                                        (called_from_id, slice.len(), slice.ptr() as usize),
+                        // Prefer a read-write buffer if one was lent. If the
+                        // read-write slot is empty, fall back to a read-only
+                        // buffer lent in the matching slot and map it without
+                        // write permission. The read-only flag is OR'd into the
+                        // shared-buffer length word so the receiver knows it may
+                        // only read the region.
+                        let rw_shared = called_from_kernel_data
+                            .get_readwrite_processbuffer(i)
+                            .map(|slice| (slice.ptr(), slice.len()))
+                            .ok()
+                            .filter(|(_, len)| *len > 0);
+                        if let Some((ptr, len)) = rw_shared {
+                            self.data
+                                .kernel
+                                .process_map_or(None, schedule_on, |process| {
+                                    process.add_mpu_region(
+                                        ptr,
+                                        len,
+                                        len,
+                                        mpu::Permissions::ReadWriteOnly,
                                     )
-                                    .ok();
-                            }
-                            Err(_) => {
-                                schedule_on_kernel_data
-                                    .schedule_upcall(to_schedule, (called_from_id, 0, 0))
-                                    .ok();
-                            }
+                                });
+                            schedule_on_kernel_data
+                                .schedule_upcall(to_schedule, (called_from_id, len, ptr as usize))
+                                .ok();
+                            return;
+                        }
+                        let ro_shared = called_from_kernel_data
+                            .get_readonly_processbuffer(ro_allow::SHARE_RO + i)
+                            .map(|slice| (slice.ptr(), slice.len()))
+                            .ok()
+                            .filter(|(_, len)| *len > 0);
+                        if let Some((ptr, len)) = ro_shared {
+                            // Map the lent region read-only so a buggy or
+                            // malicious receiver cannot mutate a buffer its owner
+                            // only lent immutably. This is the same MPU call as
+                            // the read-write path, with read-only permissions.
+                            self.data
+                                .kernel
+                                .process_map_or(None, schedule_on, |process| {
+                                    process.add_mpu_region(
+                                        ptr,
+                                        len,
+                                        len,
+                                        mpu::Permissions::ReadOnly,
+                                    )
+                                });
+                            schedule_on_kernel_data
+                                .schedule_upcall(
+                                    to_schedule,
+                                    (called_from_id, len | READONLY_SHARED_FLAG, ptr as usize),
+                                )
+                                .ok();
+                        } else {
+                            schedule_on_kernel_data
+                                .schedule_upcall(to_schedule, (called_from_id, 0, 0))
+                                .ok();
                         }
                     }
                 })
             })
             .and_then(|x| x)
     }
+
+    /// Returns whether `caller` is permitted to discover or notify `service`.
+    /// A service that has not published a client allowlist (with `command` 6)
+    /// is open to every caller, preserving the historic behaviour; otherwise
+    /// the caller's TBF package name must appear in the packed allowlist.
+    fn caller_allowed(&self, service: ProcessId, caller: ProcessId) -> bool {
+        self.data.kernel.process_map_or(false, caller, |c| {
+            let caller_name = c.get_process_name().as_bytes();
+            self.data
+                .enter(service, |app_data, _| {
+                    if !app_data.allowlist_set {
+                        return true;
+                    }
+                    let mut offset = 0;
+                    while offset < app_data.allowlist_len {
+                        let len = app_data.allowlist[offset] as usize;
+                        offset += 1;
+                        if offset + len > app_data.allowlist_len {
+                            break;
+                        }
+                        if &app_data.allowlist[offset..offset + len] == caller_name {
+                            return true;
+                        }
+                        offset += len;
+                    }
+                    false
+                })
+                // If the service's grant is unavailable (e.g. not yet
+                // allocated) it has published no allowlist, so default open.
+                .unwrap_or(true)
+        })
+    }
+
+    /// Allocate a correlation tag for a request `client` is issuing to the
+    /// service at process index `peer`, and record it in the client's pending
+    /// ring. Returns the tag, or `None` if the ring is full or the client's
+    /// grant is unavailable.
+    fn record_request(&self, client: ProcessId, peer: usize) -> Option<u32> {
+        self.data
+            .enter(client, |app_data, _| {
+                let free = app_data.pending.iter().position(|e| e.is_none())?;
+                let tag = app_data.next_tag;
+                app_data.next_tag = match app_data.next_tag.wrapping_add(1) {
+                    0 => 1,
+                    n => n,
+                };
+                app_data.pending[free] = Some((tag, peer));
+                Some(tag)
+            })
+            .ok()
+            .flatten()
+    }
+
+    /// Remove the outstanding request with tag `tag` and peer process index
+    /// `peer` from `client`'s pending ring. Returns `true` if a matching
+    /// request was found. The peer must match the entry recorded at request
+    /// time so that only the process the request was issued to can answer it:
+    /// otherwise any app could send command 5 with a guessed tag and pop
+    /// another peer's outstanding request.
+    fn take_request(&self, client: ProcessId, tag: u32, peer: usize) -> bool {
+        self.data
+            .enter(client, |app_data, _| {
+                match app_data
+                    .pending
+                    .iter()
+                    .position(|e| matches!(e, Some((t, p)) if *t == tag && *p == peer))
+                {
+                    Some(i) => {
+                        app_data.pending[i] = None;
+                        true
+                    }
+                    None => false,
+                }
+            })
+            .unwrap_or(false)
+    }
+
+    /// Record `client` as having discovered `service` so a later broadcast
+    /// (`command` 7) will fan out to it. Clients with an index beyond the
+    /// bitmask width are silently ignored.
+    fn add_client(&self, service: ProcessId, client: ProcessId) {
+        if let Some(i) = client.index() {
+            if i < u32::BITS as usize {
+                let _ = self.data.enter(service, |app_data, _| {
+                    app_data.clients |= 1 << i;
+                });
+            }
+        }
+    }
+
+    /// Resolve a descriptor's endpoint selector to the service upcall slot on
+    /// `service` that should receive the notify. Selector 0 is the primary
+    /// service; selector `k` addresses endpoint `k - 1`, which must have been
+    /// registered. Returns `None` for an out-of-range or unregistered endpoint.
+    fn endpoint_upcall_slot(&self, service: ProcessId, selector: usize) -> Option<usize> {
+        if selector == 0 {
+            return Some(SERVICE_UPCALL_NUM);
+        }
+        let endpoint = selector - 1;
+        if endpoint >= MAX_ENDPOINTS {
+            return None;
+        }
+        self.data
+            .enter(service, |app_data, _| {
+                app_data.endpoints[endpoint]
+                    .active
+                    .then_some(CLIENT_UPCALL_NUM_BASE + NUM_PROCS + endpoint)
+            })
+            .ok()
+            .flatten()
+    }
 }
 
 impl<const NUM_PROCS: usize, const NUM_UPCALLS: usize> SyscallDriver
@@ -164,11 +482,35 @@ impl<const NUM_PROCS: usize, const NUM_UPCALLS: usize> SyscallDriver
     /// - `3`: Notify a client with descriptor `target_id`, typically in response to a previous
     ///        notify from the client. Returns an error if `target_id` refers to an invalid client
     ///        or the notify fails to enqueue.
+    /// - `4`: Scalar-notify a service with descriptor `target_id`, delivering `arg2` directly to
+    ///        the service upcall with no buffer sharing and no MPU region. A fresh correlation tag
+    ///        is allocated, delivered as the third upcall argument, and returned to the caller so it
+    ///        can match the reply. Same error semantics as command 2, plus `NOMEM` if the caller
+    ///        already has `PENDING_RING_LEN` requests in flight.
+    /// - `5`: Scalar-notify a client with descriptor `target_id` in reply to an earlier request,
+    ///        with `arg2` giving the correlation tag being answered. The tag must be outstanding
+    ///        against the target client, otherwise `INVAL` is returned; on success the tag is
+    ///        delivered as the third upcall argument. Same error semantics as command 3 otherwise.
+    ///        A reply carries only the tag: the command syscall exposes one spare argument beside
+    ///        `target_id` and it is spent on the tag, so the reply payload word is always 0. A
+    ///        service returning data to a client does so with its own request (command 3/4).
+    /// - `6`: Publish a client allowlist from the packed list of package names in the
+    ///        `ro_allow::ALLOWLIST` buffer. Once published, only the named clients may discover or
+    ///        notify this process; an empty list locks the service down entirely.
+    /// - `7`: Notify every client that has discovered the calling process acting as a service,
+    ///        enqueuing a client-notify task for each in one syscall. Returns the number of notify
+    ///        tasks enqueued. This is a best-effort fan-out over the discovered set: a client that
+    ///        discovered the service but never subscribed a client upcall simply drops its task in
+    ///        the kernel, so the count is an upper bound on clients actually woken, not a
+    ///        subscriber tally (the driver cannot observe kernel-managed subscription state).
+    /// - `8`: Register a named endpoint, with `target_id` giving the endpoint index in
+    ///        `[0, MAX_ENDPOINTS)` and the name taken from the `ro_allow::ENDPOINT` buffer. Returns
+    ///        the service descriptor discovery will hand out for this endpoint.
     fn command(
         &self,
         command_number: usize,
         target_id: usize,
-        _: usize,
+        arg2: usize,
         appid: ProcessId,
     ) -> CommandReturn {
         match command_number {
@@ -176,52 +518,107 @@ impl<const NUM_PROCS: usize, const NUM_UPCALLS: usize> SyscallDriver
             1 =>
             /* Discover */
             {
+                // Copy the search string out of the caller's read-only allow
+                // buffer before walking the other processes. The per-process
+                // allowlist check, endpoint lookup and subscriber record below
+                // all re-enter a process grant — including the caller's own,
+                // when a process discovers itself — which would fail (and
+                // `caller_allowed` would then default open) if the caller's
+                // grant were still held across the walk.
+                let mut needle = [0u8; DISCOVERY_SEARCH_MAX];
+                let needle_len = self.data.enter(appid, |_, kernel_data| {
+                    kernel_data
+                        .get_readonly_processbuffer(ro_allow::SEARCH)
+                        .and_then(|search| {
+                            search.enter(|slice| {
+                                for (dst, src) in needle.iter_mut().zip(slice.iter()) {
+                                    *dst = src.get();
+                                }
+                                slice.len()
+                            })
+                        })
+                        .ok()
+                });
+                let needle_len = match needle_len {
+                    Ok(Some(len)) => len,
+                    _ => return CommandReturn::failure(ErrorCode::INVAL),
+                };
+                // A search longer than the buffer cannot match any name.
+                if needle_len > needle.len() {
+                    return CommandReturn::failure(ErrorCode::NODEVICE);
+                }
+                let needle = &needle[..needle_len];
                 self.data
-                    .enter(appid, |_, kernel_data| {
-                        kernel_data
-                            .get_readonly_processbuffer(ro_allow::SEARCH)
-                            .and_then(|search| {
-                                search.enter(|slice| {
-                                    self.data
-                                        .kernel
-                                        .process_until(|p| {
-                                            let s = p.get_process_name().as_bytes();
-                                            // are slices equal?
-                                            if s.len() == slice.len()
-                                                && s.iter()
-                                                    .zip(slice.iter())
-                                                    .all(|(c1, c2)| *c1 == c2.get())
-                                            {
-                                                // Return the index of the process which is used for
-                                                // subscribe number
-                                                p.processid()
-                                                    .index()
-                                                    .map(|i| CommandReturn::success_u32(i as u32))
-                                            } else {
-                                                None
-                                            }
-                                        })
-                                        .unwrap_or(CommandReturn::failure(ErrorCode::NODEVICE))
+                    .kernel
+                    .process_until(|p| {
+                        let pid = p.processid();
+                        let process_index = pid.index()?;
+                        // Match the search string against the union of the
+                        // process name (selector 0, the primary service) and any
+                        // registered endpoint names (selector k + 1).
+                        let selector = if p.get_process_name().as_bytes() == needle {
+                            Some(0)
+                        } else {
+                            self.data
+                                .enter(pid, |app_data, _| {
+                                    app_data.endpoints.iter().enumerate().find_map(|(k, e)| {
+                                        (e.active
+                                            && e.name_len == needle.len()
+                                            && &e.name[..e.name_len] == needle)
+                                            .then_some(k + 1)
+                                    })
                                 })
-                            })
-                            .unwrap_or(CommandReturn::failure(ErrorCode::INVAL))
+                                .ok()
+                                .flatten()
+                        };
+                        selector.map(|sel| {
+                            // Only reveal the service if the caller is on its
+                            // published allowlist, otherwise report it as absent.
+                            if !self.caller_allowed(pid, appid) {
+                                return CommandReturn::failure(ErrorCode::NODEVICE);
+                            }
+                            // Record the caller as a client of the discovered
+                            // service. Upcall subscription itself is managed by
+                            // the core kernel and not visible to this driver, so
+                            // discovery — the step every client performs before
+                            // subscribing and waiting — is the earliest point the
+                            // driver can observe the binding, and is what a later
+                            // broadcast (command 7) fans out to.
+                            self.add_client(pid, appid);
+                            CommandReturn::success_u32(
+                                make_descriptor(process_index, sel) as u32,
+                            )
+                        })
                     })
-                    .unwrap_or(CommandReturn::failure(ErrorCode::NOMEM))
+                    .unwrap_or(CommandReturn::failure(ErrorCode::NODEVICE))
             }
             2 =>
             /* Service notify */
             {
-                let cb_type = IPCUpcallType::Service;
+                // The descriptor packs the target process index and the
+                // endpoint selector.
+                let process_index = target_id & PROCESS_INDEX_MASK;
+                let selector = target_id >> ENDPOINT_SELECTOR_SHIFT;
 
                 let other_process =
                     self.data
                         .kernel
                         .process_until(|p| match p.processid().index() {
-                            Some(i) if i == target_id => Some(p.processid()),
+                            Some(i) if i == process_index => Some(p.processid()),
                             _ => None,
                         });
 
                 other_process.map_or(CommandReturn::failure(ErrorCode::INVAL), |otherapp| {
+                    // Reject the notify if the target restricts its callers and
+                    // this one is not on the allowlist.
+                    if !self.caller_allowed(otherapp, appid) {
+                        return CommandReturn::failure(ErrorCode::RESERVE);
+                    }
+                    // Resolve the addressed endpoint to the upcall slot to fire.
+                    let cb_type = match self.endpoint_upcall_slot(otherapp, selector) {
+                        Some(slot) => IPCUpcallType::Service { slot, scalar: None },
+                        None => return CommandReturn::failure(ErrorCode::INVAL),
+                    };
                     self.data.kernel.process_map_or(
                         CommandReturn::failure(ErrorCode::INVAL),
                         otherapp,
@@ -242,7 +639,7 @@ impl<const NUM_PROCS: usize, const NUM_UPCALLS: usize> SyscallDriver
             3 =>
             /* Client notify */
             {
-                let cb_type = IPCUpcallType::Client;
+                let cb_type = IPCUpcallType::Client { scalar: None };
 
                 let other_process =
                     self.data
@@ -253,6 +650,11 @@ impl<const NUM_PROCS: usize, const NUM_UPCALLS: usize> SyscallDriver
                         });
 
                 other_process.map_or(CommandReturn::failure(ErrorCode::INVAL), |otherapp| {
+                    // Reject the notify if the target restricts its callers and
+                    // this one is not on the allowlist.
+                    if !self.caller_allowed(otherapp, appid) {
+                        return CommandReturn::failure(ErrorCode::RESERVE);
+                    }
                     self.data.kernel.process_map_or(
                         CommandReturn::failure(ErrorCode::INVAL),
                         otherapp,
@@ -270,6 +672,225 @@ impl<const NUM_PROCS: usize, const NUM_UPCALLS: usize> SyscallDriver
                     )
                 })
             }
+            4 | 5 =>
+            /* Scalar notify (4 = service, 5 = client) */
+            {
+                // A service descriptor (command 4) packs the target process
+                // index and endpoint selector; a client is addressed by its
+                // bare process index (command 5).
+                let (process_index, selector) = if command_number == 4 {
+                    (target_id & PROCESS_INDEX_MASK, target_id >> ENDPOINT_SELECTOR_SHIFT)
+                } else {
+                    (target_id, 0)
+                };
+
+                let other_process =
+                    self.data
+                        .kernel
+                        .process_until(|p| match p.processid().index() {
+                            Some(i) if i == process_index => Some(p.processid()),
+                            _ => None,
+                        });
+
+                other_process.map_or(CommandReturn::failure(ErrorCode::INVAL), |otherapp| {
+                    // Reject the notify if the target restricts its callers and
+                    // this one is not on the allowlist.
+                    if !self.caller_allowed(otherapp, appid) {
+                        return CommandReturn::failure(ErrorCode::RESERVE);
+                    }
+                    // Correlation-tag handling. The command syscall exposes a
+                    // single spare argument register beside the target id, so
+                    // the inline payload is one word and the second upcall
+                    // payload word carries the tag.
+                    //
+                    // Request (command 4): allocate a fresh tag for the caller,
+                    // record it in the caller's pending ring, deliver it
+                    // alongside the payload to the service, and return it to the
+                    // caller so it can match the eventual reply.
+                    //
+                    // Reply (command 5): `arg2` is the tag being answered;
+                    // verify it is genuinely outstanding against the target
+                    // client and pop it before delivering the reply upcall.
+                    let (cb_type, assigned_tag) = if command_number == 4 {
+                        // Resolve the addressed endpoint to the upcall slot
+                        // before allocating a tag.
+                        let slot = match self.endpoint_upcall_slot(otherapp, selector) {
+                            Some(slot) => slot,
+                            None => return CommandReturn::failure(ErrorCode::INVAL),
+                        };
+                        match self.record_request(appid, process_index) {
+                            Some(tag) => (
+                                IPCUpcallType::Service {
+                                    slot,
+                                    scalar: Some((arg2, tag as usize)),
+                                },
+                                Some(tag),
+                            ),
+                            None => return CommandReturn::failure(ErrorCode::NOMEM),
+                        }
+                    } else {
+                        // The reply must come from the process the request was
+                        // issued to, so match the stored peer against the
+                        // replier's own index.
+                        let replier = match appid.index() {
+                            Some(i) => i,
+                            None => return CommandReturn::failure(ErrorCode::INVAL),
+                        };
+                        if !self.take_request(otherapp, arg2 as u32, replier) {
+                            return CommandReturn::failure(ErrorCode::INVAL);
+                        }
+                        // A reply delivers only the correlation tag: command 5
+                        // has no spare argument left for a payload word beyond
+                        // the tag in `arg2`, so the first payload word is always
+                        // 0 by ABI (see the command 5 docs).
+                        (
+                            IPCUpcallType::Client {
+                                scalar: Some((0, arg2)),
+                            },
+                            None,
+                        )
+                    };
+                    let ret = self.data.kernel.process_map_or(
+                        Err(ErrorCode::INVAL),
+                        otherapp,
+                        |target| target.enqueue_task(process::Task::IPC((appid, cb_type))),
+                    );
+                    match ret {
+                        Ok(()) => {
+                            assigned_tag.map_or(CommandReturn::success(), CommandReturn::success_u32)
+                        }
+                        // The other side has a null upcall, so the request was
+                        // never delivered and will never be answered. Reclaim
+                        // the tag we recorded instead of leaking a ring slot,
+                        // and report success per the notify contract.
+                        Err(ErrorCode::OFF) => {
+                            if let Some(tag) = assigned_tag {
+                                self.take_request(appid, tag, process_index);
+                            }
+                            CommandReturn::success()
+                        }
+                        Err(e) => {
+                            if let Some(tag) = assigned_tag {
+                                self.take_request(appid, tag, process_index);
+                            }
+                            CommandReturn::failure(e)
+                        }
+                    }
+                })
+            }
+            6 =>
+            /* Register client allowlist */
+            {
+                self.data
+                    .enter(appid, |app_data, kernel_data| {
+                        kernel_data
+                            .get_readonly_processbuffer(ro_allow::ALLOWLIST)
+                            .and_then(|buffer| {
+                                buffer.enter(|slice| {
+                                    let len =
+                                        core::cmp::min(slice.len(), app_data.allowlist.len());
+                                    for (dst, src) in
+                                        app_data.allowlist.iter_mut().zip(slice.iter())
+                                    {
+                                        *dst = src.get();
+                                    }
+                                    app_data.allowlist_len = len;
+                                    app_data.allowlist_set = true;
+                                    CommandReturn::success()
+                                })
+                            })
+                            .unwrap_or(CommandReturn::failure(ErrorCode::INVAL))
+                    })
+                    .unwrap_or(CommandReturn::failure(ErrorCode::NOMEM))
+            }
+            7 =>
+            /* Notify all discovered clients */
+            {
+                // Snapshot the discovered-client set for the calling service,
+                // then enqueue a client-notify task for each client that still
+                // exists. Count only the enqueues the kernel accepted, and
+                // collect bits for clients whose process no longer exists so
+                // they can be pruned (a reused index must not misdirect a
+                // future broadcast).
+                let clients = self
+                    .data
+                    .enter(appid, |app_data, _| app_data.clients)
+                    .unwrap_or(0);
+                let mut notified: u32 = 0;
+                let mut stale: u32 = 0;
+                for i in 0..u32::BITS as usize {
+                    if clients & (1 << i) == 0 {
+                        continue;
+                    }
+                    let client = self
+                        .data
+                        .kernel
+                        .process_until(|p| match p.processid().index() {
+                            Some(idx) if idx == i => Some(p.processid()),
+                            _ => None,
+                        });
+                    match client {
+                        Some(clientid) => {
+                            // Skip clients whose published allowlist excludes
+                            // this service, as commands 3 and 5 do.
+                            let delivered = self.caller_allowed(clientid, appid)
+                                && self.data.kernel.process_map_or(false, clientid, |target| {
+                                    target
+                                        .enqueue_task(process::Task::IPC((
+                                            appid,
+                                            IPCUpcallType::Client { scalar: None },
+                                        )))
+                                        .is_ok()
+                                });
+                            if delivered {
+                                notified += 1;
+                            }
+                        }
+                        // The discovered process is gone; drop it from the set.
+                        None => stale |= 1 << i,
+                    }
+                }
+                if stale != 0 {
+                    let _ = self.data.enter(appid, |app_data, _| {
+                        app_data.clients &= !stale;
+                    });
+                }
+                CommandReturn::success_u32(notified)
+            }
+            8 =>
+            /* Register named endpoint */
+            {
+                if target_id >= MAX_ENDPOINTS {
+                    return CommandReturn::failure(ErrorCode::INVAL);
+                }
+                let endpoint = target_id;
+                self.data
+                    .enter(appid, |app_data, kernel_data| {
+                        kernel_data
+                            .get_readonly_processbuffer(ro_allow::ENDPOINT)
+                            .and_then(|buffer| {
+                                buffer.enter(|slice| {
+                                    let e = &mut app_data.endpoints[endpoint];
+                                    let len = core::cmp::min(slice.len(), e.name.len());
+                                    for (dst, src) in e.name.iter_mut().zip(slice.iter()) {
+                                        *dst = src.get();
+                                    }
+                                    e.name_len = len;
+                                    e.active = true;
+                                    appid.index().map_or(
+                                        CommandReturn::failure(ErrorCode::INVAL),
+                                        |i| {
+                                            CommandReturn::success_u32(
+                                                make_descriptor(i, endpoint + 1) as u32,
+                                            )
+                                        },
+                                    )
+                                })
+                            })
+                            .unwrap_or(CommandReturn::failure(ErrorCode::INVAL))
+                    })
+                    .unwrap_or(CommandReturn::failure(ErrorCode::NOMEM))
+            }
             _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
         }
     }